@@ -0,0 +1,144 @@
+pub trait Mem {
+    fn mem_read(&mut self, addr: u16) -> u8;
+    fn mem_write(&mut self, addr: u16, data: u8);
+
+    fn mem_read_u16(&mut self, pos: u16) -> u16 {
+        let lo = self.mem_read(pos) as u16;
+        let hi = self.mem_read(pos + 1) as u16;
+        (hi << 8) | lo
+    }
+
+    fn mem_write_u16(&mut self, pos: u16, data: u16) {
+        let hi = (data >> 8) as u8;
+        let lo = (data & 0xFF) as u8;
+        self.mem_write(pos, lo);
+        self.mem_write(pos + 1, hi);
+    }
+}
+
+const RAM: u16 = 0x0000;
+const RAM_MIRRORS_END: u16 = 0x1FFF;
+const PPU_REGISTERS: u16 = 0x2000;
+const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
+const PRG_ROM: u16 = 0x8000;
+const PRG_ROM_END: u16 = 0xFFFF;
+
+// Which device on the bus an address belongs to, along with the address.
+// RAM and PPU-register addresses arrive with their mirrors already folded
+// onto the base range; PRG ROM addresses are still raw CPU addresses, and
+// `read_prg_rom` turns them into ROM offsets.
+enum MemoryRegion {
+    Ram(u16),
+    PpuRegisters(u16),
+    PrgRom(u16),
+    Unmapped(u16),
+}
+
+pub struct Bus {
+    cpu_vram: [u8; 2048],
+    prg_rom: Vec<u8>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Bus::new()
+    }
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        Bus {
+            cpu_vram: [0; 2048],
+            prg_rom: Vec::new(),
+        }
+    }
+
+    pub fn load_prg_rom(&mut self, rom: Vec<u8>) {
+        self.prg_rom = rom;
+    }
+
+    // A cartridge's reset vector is baked into PRG ROM at mastering time;
+    // there's no bus write path for it since ROM is read-only once mounted.
+    pub fn set_reset_vector(&mut self, addr: u16) {
+        let offset = (PRG_ROM_END - 3 - PRG_ROM) as usize; // 0xFFFC - 0x8000
+        if self.prg_rom.len() < offset + 2 {
+            self.prg_rom.resize(offset + 2, 0);
+        }
+        self.prg_rom[offset] = (addr & 0xFF) as u8;
+        self.prg_rom[offset + 1] = (addr >> 8) as u8;
+    }
+
+    fn get_region(addr: u16) -> MemoryRegion {
+        match addr {
+            RAM..=RAM_MIRRORS_END => {
+                // Internal RAM is 2KB but wired up through 0x1FFF, repeating
+                // every 0x0800 bytes.
+                MemoryRegion::Ram(addr & 0b0000_0111_1111_1111)
+            }
+            PPU_REGISTERS..=PPU_REGISTERS_MIRRORS_END => {
+                // The 8 PPU registers repeat every 8 bytes through 0x3FFF.
+                MemoryRegion::PpuRegisters(addr & 0b0010_0000_0000_0111)
+            }
+            PRG_ROM..=PRG_ROM_END => MemoryRegion::PrgRom(addr),
+            _ => MemoryRegion::Unmapped(addr),
+        }
+    }
+
+    fn read_prg_rom(&self, addr: u16) -> u8 {
+        let mut addr = addr - PRG_ROM;
+        if self.prg_rom.len() == 0x4000 && addr >= 0x4000 {
+            // 16KB cartridges are mirrored into the upper half of the window.
+            addr %= 0x4000;
+        }
+        // Test programs only populate a handful of bytes plus the reset
+        // vector; anything else in the PRG ROM window reads as unprogrammed.
+        self.prg_rom.get(addr as usize).copied().unwrap_or(0)
+    }
+}
+
+impl Mem for Bus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        match Bus::get_region(addr) {
+            MemoryRegion::Ram(mirrored_addr) => self.cpu_vram[mirrored_addr as usize],
+            MemoryRegion::PpuRegisters(_mirrored_addr) => {
+                // The PPU itself isn't emulated yet; stub out reads rather
+                // than panicking so CPU-only test programs can still poke
+                // its register range without crashing.
+                0
+            }
+            MemoryRegion::PrgRom(addr) => self.read_prg_rom(addr),
+            MemoryRegion::Unmapped(addr) => {
+                println!("Ignoring mem read at {:x}", addr);
+                0
+            }
+        }
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        match Bus::get_region(addr) {
+            MemoryRegion::Ram(mirrored_addr) => self.cpu_vram[mirrored_addr as usize] = data,
+            MemoryRegion::PpuRegisters(_mirrored_addr) => {
+                // No-op until the PPU is emulated; see the read side above.
+            }
+            MemoryRegion::PrgRom(_) => {
+                panic!("Attempt to write to Cartridge ROM space")
+            }
+            MemoryRegion::Unmapped(addr) => {
+                println!("Ignoring mem write at {:x}", addr);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ppu_register_access_does_not_panic() {
+        let mut bus = Bus::new();
+        bus.mem_write(0x2000, 0xFF);
+        assert_eq!(bus.mem_read(0x2000), 0);
+        assert_eq!(bus.mem_read(0x3FFF), 0); // mirrors 0x2007
+    }
+}
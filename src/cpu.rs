@@ -1,46 +1,173 @@
+use crate::bus::{Bus, Mem};
+use crate::opcodes::{self, OpCode};
+
+const FLAG_CARRY: u8 = 0b0000_0001;
+const FLAG_ZERO: u8 = 0b0000_0010;
+const FLAG_INTERRUPT_DISABLE: u8 = 0b0000_0100;
+const FLAG_DECIMAL: u8 = 0b0000_1000;
+const FLAG_BREAK: u8 = 0b0001_0000;
+const FLAG_BREAK2: u8 = 0b0010_0000;
+const FLAG_OVERFLOW: u8 = 0b0100_0000;
+const FLAG_NEGATIVE: u8 = 0b1000_0000;
+
+const STACK: u16 = 0x0100;
+const STACK_RESET: u8 = 0xFD;
+
+// Which 6502-family instruction set the CPU decodes opcodes as. The two
+// variants share almost all behavior; they diverge on a handful of
+// historical quirks (see `AddressingMode::Indirect` in
+// `get_operand_address`) and, for 65C02, a handful of extra opcodes not
+// implemented here yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Nmos6502,
+    Cmos65c02,
+}
+
 pub struct CPU {
     pub register_a: u8,
     pub register_x: u8,
+    pub register_y: u8,
+    pub register_sp: u8,
     pub status: u8,
     pub program_counter: u16,
-    memory: [u8; 0xFFFF],
+    variant: Variant,
+    bus: Bus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    NoneAddressing,
 }
 
 impl CPU {
-    pub fn new() -> Self {
+    pub fn new(variant: Variant) -> Self {
         CPU {
             register_a: 0,
             register_x: 0,
+            register_y: 0,
+            register_sp: STACK_RESET,
             status: 0,
             program_counter: 0,
-            memory: [0; 0xFFFF],
+            variant,
+            bus: Bus::new(),
         }
     }
 
-    pub fn mem_read_u16(&mut self, pos: u16) -> u16 {
-        let lo = self.mem_read(pos) as u16;
-        let hi = self.mem_read(pos + 1) as u16;
-        (hi << 8) | lo
+    // Computes the effective address an instruction operates on, given its
+    // addressing mode. `program_counter` is expected to point at the first
+    // operand byte of the current instruction.
+    fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
+        match mode {
+            AddressingMode::Immediate => self.program_counter,
+
+            AddressingMode::ZeroPage => self.mem_read(self.program_counter) as u16,
+
+            AddressingMode::Absolute => self.mem_read_u16(self.program_counter),
+
+            AddressingMode::ZeroPageX => {
+                let pos = self.mem_read(self.program_counter);
+                pos.wrapping_add(self.register_x) as u16
+            }
+            AddressingMode::ZeroPageY => {
+                let pos = self.mem_read(self.program_counter);
+                pos.wrapping_add(self.register_y) as u16
+            }
+
+            AddressingMode::AbsoluteX => {
+                let base = self.mem_read_u16(self.program_counter);
+                base.wrapping_add(self.register_x as u16)
+            }
+            AddressingMode::AbsoluteY => {
+                let base = self.mem_read_u16(self.program_counter);
+                base.wrapping_add(self.register_y as u16)
+            }
+
+            AddressingMode::Indirect => {
+                let ptr = self.mem_read_u16(self.program_counter);
+
+                if ptr & 0x00FF == 0x00FF {
+                    match self.variant {
+                        Variant::Nmos6502 => {
+                            // The original 6502 fails to carry into the high
+                            // byte when the pointer's low byte is 0xFF,
+                            // wrapping within the same page instead of
+                            // crossing into the next one.
+                            let lo = self.mem_read(ptr);
+                            let hi = self.mem_read(ptr & 0xFF00);
+                            (hi as u16) << 8 | (lo as u16)
+                        }
+                        Variant::Cmos65c02 => self.mem_read_u16(ptr),
+                    }
+                } else {
+                    self.mem_read_u16(ptr)
+                }
+            }
+
+            AddressingMode::IndirectX => {
+                let base = self.mem_read(self.program_counter);
+
+                let ptr = base.wrapping_add(self.register_x);
+                let lo = self.mem_read(ptr as u16);
+                let hi = self.mem_read(ptr.wrapping_add(1) as u16);
+                (hi as u16) << 8 | (lo as u16)
+            }
+            AddressingMode::IndirectY => {
+                let base = self.mem_read(self.program_counter);
+
+                let lo = self.mem_read(base as u16);
+                let hi = self.mem_read((base).wrapping_add(1) as u16);
+                let deref_base = (hi as u16) << 8 | (lo as u16);
+                deref_base.wrapping_add(self.register_y as u16)
+            }
+
+            AddressingMode::NoneAddressing => {
+                panic!("mode {:?} is not supported", mode);
+            }
+        }
     }
 
-    pub fn mem_write_u16(&mut self, pos: u16, data: u16) {
-        let hi = (data >> 8) as u8;
-        let lo = (data & 0b0000_1111) as u8;
-        self.mem_write(pos, lo);
-        self.mem_write(pos + 1, hi);
+    fn stack_push(&mut self, data: u8) {
+        self.mem_write(STACK + self.register_sp as u16, data);
+        self.register_sp = self.register_sp.wrapping_sub(1);
     }
 
-    pub fn mem_read(&self, addr: u16) -> u8 {
-        self.memory[addr as usize]
+    fn stack_pop(&mut self) -> u8 {
+        self.register_sp = self.register_sp.wrapping_add(1);
+        self.mem_read(STACK + self.register_sp as u16)
     }
-    pub fn mem_write(&mut self, addr: u16, data: u8) {
-        self.memory[addr as usize] = data;
+
+    fn stack_push_u16(&mut self, data: u16) {
+        let hi = (data >> 8) as u8;
+        let lo = (data & 0xFF) as u8;
+        self.stack_push(hi);
+        self.stack_push(lo);
+    }
+
+    fn stack_pop_u16(&mut self) -> u16 {
+        let lo = self.stack_pop() as u16;
+        let hi = self.stack_pop() as u16;
+        (hi << 8) | lo
     }
 
     pub fn reset(&mut self) {
         self.register_x = 0;
+        self.register_y = 0;
         self.register_a = 0;
-        self.status = 0;
+        self.register_sp = STACK_RESET;
+        // Real hardware reset leaves the IRQ-disable and unused bits set
+        // (P:24), not all bits clear.
+        self.status = FLAG_INTERRUPT_DISABLE | FLAG_BREAK2;
 
         self.program_counter = self.mem_read_u16(0xFFFC)
     }
@@ -52,17 +179,140 @@ impl CPU {
     }
 
     pub fn load(&mut self, program: Vec<u8>) {
-        self.memory[0x8000..(0x8000 + program.len())].copy_from_slice(&program);
-        // Load to PC at address 0xFFFC
-        self.mem_write_u16(0xFFFC, 0x8000);
+        self.bus.load_prg_rom(program);
+        self.bus.set_reset_vector(0x8000);
     }
 
     // (LDA) Load accumulator
-    fn lda(&mut self, value: u8) {
+    fn lda(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let value = self.mem_read(addr);
+
         self.register_a = value;
         self.update_zero_and_negative_flags(self.register_a);
     }
 
+    // (ADC) Add with carry
+    fn adc(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let value = self.mem_read(addr);
+
+        self.add_with_carry(value);
+    }
+
+    // (SBC) Subtract with carry
+    fn sbc(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let value = self.mem_read(addr);
+
+        if self.status & FLAG_DECIMAL != 0 {
+            // BCD subtraction borrows by 10 per digit, not by 16, so it
+            // can't be routed through the BCD *add* helper the way binary
+            // SBC is routed through binary ADC.
+            self.subtract_with_carry_decimal(value);
+        } else {
+            // A - M - (1 - C) is the same addition circuit as ADC fed the
+            // one's complement of the operand.
+            self.add_with_carry(value ^ 0xFF);
+        }
+    }
+
+    fn add_with_carry(&mut self, value: u8) {
+        if self.status & FLAG_DECIMAL != 0 {
+            self.add_with_carry_decimal(value);
+            return;
+        }
+
+        let carry_in = (self.status & FLAG_CARRY) as u16;
+        let sum = self.register_a as u16 + value as u16 + carry_in;
+        let result = sum as u8;
+
+        if sum > 0xFF {
+            self.status |= FLAG_CARRY;
+        } else {
+            self.status &= !FLAG_CARRY;
+        }
+
+        if (value ^ result) & (self.register_a ^ result) & 0x80 != 0 {
+            self.status |= FLAG_OVERFLOW;
+        } else {
+            self.status &= !FLAG_OVERFLOW;
+        }
+
+        self.register_a = result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // NMOS 6502 decimal-mode addition: BCD-correct the binary sum nibble by
+    // nibble, since the chip's adder itself is purely binary. N/V/Z still
+    // reflect the uncorrected binary sum, exactly as the real hardware's
+    // flag logic does — only A and C get the BCD correction.
+    fn add_with_carry_decimal(&mut self, value: u8) {
+        let carry_in = (self.status & FLAG_CARRY) as u16;
+
+        let binary_sum = self.register_a as u16 + value as u16 + carry_in;
+        let binary_result = binary_sum as u8;
+        if (value ^ binary_result) & (self.register_a ^ binary_result) & 0x80 != 0 {
+            self.status |= FLAG_OVERFLOW;
+        } else {
+            self.status &= !FLAG_OVERFLOW;
+        }
+
+        let mut lo = (self.register_a & 0x0F) as u16 + (value & 0x0F) as u16 + carry_in;
+        if lo > 9 {
+            lo += 6;
+        }
+
+        let mut hi = (self.register_a >> 4) as u16 + (value >> 4) as u16 + if lo > 0x0F { 1 } else { 0 };
+        if hi > 9 {
+            hi += 6;
+        }
+
+        if hi > 0x0F {
+            self.status |= FLAG_CARRY;
+        } else {
+            self.status &= !FLAG_CARRY;
+        }
+
+        self.register_a = (((hi & 0x0F) << 4) | (lo & 0x0F)) as u8;
+        self.update_zero_and_negative_flags(binary_result);
+    }
+
+    // NMOS 6502 decimal-mode subtraction. Each BCD digit borrows by 10
+    // rather than by 16, so this can't reuse the binary adder the way
+    // binary SBC reuses it via the one's complement trick. N/V/Z mirror
+    // add_with_carry_decimal's convention: they reflect the binary
+    // difference, not the BCD-corrected one.
+    fn subtract_with_carry_decimal(&mut self, value: u8) {
+        let carry_in = (self.status & FLAG_CARRY) as i16;
+        let complement = value ^ 0xFF;
+
+        let binary_diff = self.register_a as i16 + complement as i16 + carry_in;
+        let binary_result = binary_diff as u8;
+        if (complement ^ binary_result) & (self.register_a ^ binary_result) & 0x80 != 0 {
+            self.status |= FLAG_OVERFLOW;
+        } else {
+            self.status &= !FLAG_OVERFLOW;
+        }
+
+        let borrow_in = 1 - carry_in;
+        let mut lo = (self.register_a & 0x0F) as i16 - (value & 0x0F) as i16 - borrow_in;
+        let mut hi = (self.register_a >> 4) as i16 - (value >> 4) as i16;
+        if lo < 0 {
+            lo += 10;
+            hi -= 1;
+        }
+        if hi < 0 {
+            hi += 10;
+            self.status &= !FLAG_CARRY; // borrow occurred
+        } else {
+            self.status |= FLAG_CARRY; // no borrow
+        }
+
+        self.register_a = (((hi & 0x0F) << 4) | (lo & 0x0F)) as u8;
+        self.update_zero_and_negative_flags(binary_result);
+    }
+
     // (TAX) Transfer to accumulator X
     fn tax(&mut self) {
         self.register_x = self.register_a;
@@ -75,45 +325,361 @@ impl CPU {
         self.update_zero_and_negative_flags(self.register_x);
     }
 
+    // (SEC) Set carry flag
+    fn sec(&mut self) {
+        self.status |= FLAG_CARRY;
+    }
+
+    // (CLC) Clear carry flag
+    fn clc(&mut self) {
+        self.status &= !FLAG_CARRY;
+    }
+
+    // (PHA) Push accumulator
+    fn pha(&mut self) {
+        self.stack_push(self.register_a);
+    }
+
+    // (PLA) Pull accumulator
+    fn pla(&mut self) {
+        self.register_a = self.stack_pop();
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // (PHP) Push processor status
+    fn php(&mut self) {
+        // B and the unused bit are always pushed set, per the 6502 manual.
+        self.stack_push(self.status | FLAG_BREAK | FLAG_BREAK2);
+    }
+
+    // (PLP) Pull processor status
+    fn plp(&mut self) {
+        self.status = self.stack_pop();
+        self.status &= !FLAG_BREAK;
+        self.status |= FLAG_BREAK2;
+    }
+
+    // (JMP) Jump
+    fn jmp(&mut self, mode: &AddressingMode) {
+        self.program_counter = self.get_operand_address(mode);
+    }
+
+    // (JSR) Jump to subroutine
+    fn jsr(&mut self) {
+        // The spec pushes the address of the last byte of the JSR
+        // instruction, not the address of the next one.
+        self.stack_push_u16(self.program_counter + 2 - 1);
+        let addr = self.get_operand_address(&AddressingMode::Absolute);
+        self.program_counter = addr;
+    }
+
+    // (RTS) Return from subroutine
+    fn rts(&mut self) {
+        self.program_counter = self.stack_pop_u16() + 1;
+    }
+
+    // (BRK) Force interrupt
+    fn brk(&mut self) {
+        self.stack_push_u16(self.program_counter + 1);
+        self.stack_push(self.status | FLAG_BREAK | FLAG_BREAK2);
+        self.status |= FLAG_INTERRUPT_DISABLE;
+        self.program_counter = self.mem_read_u16(0xFFFE);
+    }
+
+    // (RTI) Return from interrupt
+    fn rti(&mut self) {
+        self.status = self.stack_pop();
+        self.status &= !FLAG_BREAK;
+        self.status |= FLAG_BREAK2;
+        self.program_counter = self.stack_pop_u16();
+    }
+
+    // Services a non-maskable interrupt (e.g. PPU vblank), pushing PC/status
+    // and jumping through the NMI vector, like `brk` does for the IRQ/BRK one.
+    pub fn interrupt_nmi(&mut self) {
+        self.stack_push_u16(self.program_counter);
+        self.stack_push((self.status | FLAG_BREAK2) & !FLAG_BREAK);
+        self.status |= FLAG_INTERRUPT_DISABLE;
+        self.program_counter = self.mem_read_u16(0xFFFA);
+    }
+
     fn update_zero_and_negative_flags(&mut self, register: u8) {
-        // Zero Flag (Bit 1)
         if register == 0 {
-            self.status = self.status | 0b0000_0010; // SET
+            self.status |= FLAG_ZERO;
         } else {
-            self.status = self.status & 0b1111_1101; // CLEAR
+            self.status &= !FLAG_ZERO;
         }
 
-        // Negative flag (Bit 7)
-        if register & 0b1000_0000 != 0 {
-            self.status = self.status | 0b1000_0000; // SET
+        if register & FLAG_NEGATIVE != 0 {
+            self.status |= FLAG_NEGATIVE;
         } else {
-            self.status = self.status & 0b0111_1111; // CLEAR
+            self.status &= !FLAG_NEGATIVE;
         }
     }
 
     pub fn run(&mut self) {
+        self.run_with_callback(|_| {});
+    }
+
+    // Like `run`, but invokes `callback` once per instruction before it is
+    // decoded, with the program counter pointing at its opcode byte. Lets
+    // callers (tests, a disassembling trace) observe every step.
+    pub fn run_with_callback<F>(&mut self, mut callback: F)
+    where
+        F: FnMut(&mut CPU),
+    {
         loop {
+            callback(self);
+
             let opscode = self.mem_read(self.program_counter); // Starts at 32768
             self.program_counter += 1;
 
             match opscode {
                 0xA9 => {
-                    let param = self.mem_read(self.program_counter);
+                    self.lda(&AddressingMode::Immediate);
                     self.program_counter += 1;
+                }
+                0xA5 => {
+                    self.lda(&AddressingMode::ZeroPage);
+                    self.program_counter += 1;
+                }
+                0xB5 => {
+                    self.lda(&AddressingMode::ZeroPageX);
+                    self.program_counter += 1;
+                }
+                0xAD => {
+                    self.lda(&AddressingMode::Absolute);
+                    self.program_counter += 2;
+                }
+                0xBD => {
+                    self.lda(&AddressingMode::AbsoluteX);
+                    self.program_counter += 2;
+                }
+                0xB9 => {
+                    self.lda(&AddressingMode::AbsoluteY);
+                    self.program_counter += 2;
+                }
+                0xA1 => {
+                    self.lda(&AddressingMode::IndirectX);
+                    self.program_counter += 1;
+                }
+                0xB1 => {
+                    self.lda(&AddressingMode::IndirectY);
+                    self.program_counter += 1;
+                }
 
-                    self.lda(param);
+                0x69 => {
+                    self.adc(&AddressingMode::Immediate);
+                    self.program_counter += 1;
+                }
+                0x65 => {
+                    self.adc(&AddressingMode::ZeroPage);
+                    self.program_counter += 1;
+                }
+                0x75 => {
+                    self.adc(&AddressingMode::ZeroPageX);
+                    self.program_counter += 1;
+                }
+                0x6D => {
+                    self.adc(&AddressingMode::Absolute);
+                    self.program_counter += 2;
+                }
+                0x7D => {
+                    self.adc(&AddressingMode::AbsoluteX);
+                    self.program_counter += 2;
+                }
+                0x79 => {
+                    self.adc(&AddressingMode::AbsoluteY);
+                    self.program_counter += 2;
+                }
+                0x61 => {
+                    self.adc(&AddressingMode::IndirectX);
+                    self.program_counter += 1;
+                }
+                0x71 => {
+                    self.adc(&AddressingMode::IndirectY);
+                    self.program_counter += 1;
                 }
 
+                0xE9 => {
+                    self.sbc(&AddressingMode::Immediate);
+                    self.program_counter += 1;
+                }
+                0xE5 => {
+                    self.sbc(&AddressingMode::ZeroPage);
+                    self.program_counter += 1;
+                }
+                0xF5 => {
+                    self.sbc(&AddressingMode::ZeroPageX);
+                    self.program_counter += 1;
+                }
+                0xED => {
+                    self.sbc(&AddressingMode::Absolute);
+                    self.program_counter += 2;
+                }
+                0xFD => {
+                    self.sbc(&AddressingMode::AbsoluteX);
+                    self.program_counter += 2;
+                }
+                0xF9 => {
+                    self.sbc(&AddressingMode::AbsoluteY);
+                    self.program_counter += 2;
+                }
+                0xE1 => {
+                    self.sbc(&AddressingMode::IndirectX);
+                    self.program_counter += 1;
+                }
+                0xF1 => {
+                    self.sbc(&AddressingMode::IndirectY);
+                    self.program_counter += 1;
+                }
+
+                0x38 => self.sec(),
+                0x18 => self.clc(),
+
                 0xAA => self.tax(),
 
                 0xE8 => self.inx(),
 
-                0x00 => return,
+                0x48 => self.pha(),
+                0x68 => self.pla(),
+                0x08 => self.php(),
+                0x28 => self.plp(),
+
+                0x4C => self.jmp(&AddressingMode::Absolute),
+                0x6C => self.jmp(&AddressingMode::Indirect),
+
+                0x20 => self.jsr(),
+                0x60 => self.rts(),
+                0x40 => self.rti(),
+
+                // BRK: computes the IRQ/BRK-vector jump target like real
+                // hardware would, but still ends this run() call — there is
+                // no handler ROM installed for it to resume into yet.
+                0x00 => {
+                    self.brk();
+                    return;
+                }
 
                 _ => todo!(""),
             }
         }
     }
+
+    // Disassembles the instruction at the current program counter into a
+    // nestest.log-style trace line, without advancing any CPU state.
+    pub fn trace(&mut self) -> String {
+        let begin_pc = self.program_counter;
+        let code = self.mem_read(begin_pc);
+        let op = opcodes::lookup(code)
+            .unwrap_or_else(|| panic!("unknown opcode {:02X} at {:04X}", code, begin_pc));
+
+        let mut hex_bytes = Vec::with_capacity(op.len as usize);
+        hex_bytes.push(code);
+        for offset in 1..op.len {
+            hex_bytes.push(self.mem_read(begin_pc + offset as u16));
+        }
+        let hex_str = hex_bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let asm = format!("{} {}", op.mnemonic, self.format_operand(&op, begin_pc));
+
+        format!(
+            "{:04X}  {:<8}  {:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            begin_pc,
+            hex_str,
+            asm.trim_end(),
+            self.register_a,
+            self.register_x,
+            self.register_y,
+            self.status,
+            self.register_sp
+        )
+    }
+
+    // Renders an instruction's operand the way nestest.log does: the operand
+    // as written in assembly, plus an `@ addr = value` annotation for modes
+    // that dereference memory.
+    fn format_operand(&mut self, op: &OpCode, instruction_pc: u16) -> String {
+        if op.mode == AddressingMode::NoneAddressing {
+            return String::new();
+        }
+
+        // get_operand_address reads from program_counter, so point it at the
+        // operand bytes for the duration of this call, then put it back.
+        self.program_counter = instruction_pc + 1;
+        let addr = self.get_operand_address(&op.mode);
+        self.program_counter = instruction_pc;
+
+        match op.mode {
+            AddressingMode::Immediate => format!("#${:02X}", self.mem_read(instruction_pc + 1)),
+
+            AddressingMode::ZeroPage => format!("${:02X} = {:02X}", addr, self.mem_read(addr)),
+
+            AddressingMode::ZeroPageX => format!(
+                "${:02X},X @ {:02X} = {:02X}",
+                self.mem_read(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+            AddressingMode::ZeroPageY => format!(
+                "${:02X},Y @ {:02X} = {:02X}",
+                self.mem_read(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+
+            AddressingMode::Absolute if op.mnemonic == "JSR" || op.mnemonic == "JMP" => {
+                format!("${:04X}", addr)
+            }
+            AddressingMode::Absolute => format!("${:04X} = {:02X}", addr, self.mem_read(addr)),
+
+            AddressingMode::Indirect => {
+                format!("(${:04X}) = {:04X}", self.mem_read_u16(instruction_pc + 1), addr)
+            }
+
+            AddressingMode::AbsoluteX => format!(
+                "${:04X},X @ {:04X} = {:02X}",
+                self.mem_read_u16(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+            AddressingMode::AbsoluteY => format!(
+                "${:04X},Y @ {:04X} = {:02X}",
+                self.mem_read_u16(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+
+            AddressingMode::IndirectX => format!(
+                "(${:02X},X) @ {:04X} = {:02X}",
+                self.mem_read(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+            AddressingMode::IndirectY => format!(
+                "(${:02X}),Y @ {:04X} = {:02X}",
+                self.mem_read(instruction_pc + 1),
+                addr,
+                self.mem_read(addr)
+            ),
+
+            AddressingMode::NoneAddressing => unreachable!(),
+        }
+    }
+}
+
+impl Mem for CPU {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.bus.mem_read(addr)
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.bus.mem_write(addr, data)
+    }
 }
 
 // Testing
@@ -123,7 +689,7 @@ mod test {
 
     #[test]
     fn test_0xa9_lda_immediate_load_data() {
-        let mut cpu = CPU::new();
+        let mut cpu = CPU::new(Variant::Nmos6502);
         cpu.load_and_run(vec![0xa9, 0x05, 0x00]);
         assert_eq!(cpu.register_a, 0x05);
         assert!(cpu.status & 0b0000_0010 == 0);
@@ -132,14 +698,14 @@ mod test {
 
     #[test]
     fn test_0xa9_lda_zero_flag() {
-        let mut cpu = CPU::new();
+        let mut cpu = CPU::new(Variant::Nmos6502);
         cpu.load_and_run(vec![0xa9, 0x00, 0x00]);
         assert!(cpu.status & 0b0000_0010 == 0b10);
     }
 
     #[test]
     fn test_0xaa_tax_move_a_to_x() {
-        let mut cpu = CPU::new();
+        let mut cpu = CPU::new(Variant::Nmos6502);
 
         cpu.load(vec![0xaa, 0x00]);
         cpu.reset();
@@ -152,14 +718,14 @@ mod test {
 
     #[test]
     fn test_5_ops_working_together() {
-        let mut cpu = CPU::new();
+        let mut cpu = CPU::new(Variant::Nmos6502);
         cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
         assert_eq!(cpu.register_x, 0xc1);
     }
 
     #[test]
     fn test_inx_overflow() {
-        let mut cpu = CPU::new();
+        let mut cpu = CPU::new(Variant::Nmos6502);
 
         cpu.load(vec![0xe8, 0xe8, 0x00]);
         cpu.reset();
@@ -169,4 +735,132 @@ mod test {
 
         assert_eq!(cpu.register_x, 1);
     }
+
+    #[test]
+    fn test_lda_from_memory() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x55);
+
+        cpu.load_and_run(vec![0xa5, 0x10, 0x00]);
+
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_adc_sets_carry_and_overflow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x7f, 0x69, 0x01, 0x00]);
+
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.status & 0b0100_0000 != 0); // Overflow
+        assert!(cpu.status & 0b0000_0001 == 0); // Carry
+    }
+
+    #[test]
+    fn test_sbc_without_borrow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // SEC; LDA #$10; SBC #$01 -> 0x0F, carry stays set (no borrow)
+        cpu.load_and_run(vec![0x38, 0xa9, 0x10, 0xe9, 0x01, 0x00]);
+
+        assert_eq!(cpu.register_a, 0x0f);
+    }
+
+    #[test]
+    fn test_sbc_decimal_mode_borrows_by_ten() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0xe9, 0x01, 0x00]); // SBC #$01
+        cpu.reset();
+        // A=0x00 with D and C set: 0x00 - 0x01 borrows to 0x99 in BCD,
+        // unlike the 0x65 a naive ones-complement-into-BCD-add would give.
+        cpu.status |= FLAG_DECIMAL | FLAG_CARRY;
+        cpu.run();
+
+        assert_eq!(cpu.register_a, 0x99);
+        assert!(cpu.status & FLAG_CARRY == 0); // Carry clear: borrow occurred
+    }
+
+    #[test]
+    fn test_pha_pla_roundtrip() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0x48, 0xa9, 0x00, 0x68, 0x00]);
+
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_jsr_rts() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // JSR $8005; BRK ... ; $8005: INX; RTS
+        cpu.load_and_run(vec![0x20, 0x05, 0x80, 0x00, 0x00, 0xe8, 0x60]);
+
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_trace_immediate_and_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x55);
+        cpu.load(vec![0xa5, 0x10, 0xa9, 0x05, 0x00]);
+        cpu.reset();
+
+        let lines: Vec<String> = Vec::new();
+        let lines = std::cell::RefCell::new(lines);
+        cpu.run_with_callback(|cpu| {
+            lines.borrow_mut().push(cpu.trace());
+        });
+        let lines = lines.into_inner();
+
+        assert_eq!(lines[0], "8000  A5 10     LDA $10 = 55                    A:00 X:00 Y:00 P:24 SP:FD");
+        assert_eq!(lines[1], "8002  A9 05     LDA #$05                        A:55 X:00 Y:00 P:24 SP:FD");
+    }
+
+    #[test]
+    fn test_trace_matches_nestest_log_golden_line() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // $C000 is nestest.log's documented entry point; put a JMP there
+        // directly (bypassing load()'s $8000 reset vector) to diff against
+        // the real first line of the golden log byte for byte.
+        let mut rom = vec![0u8; 0x4003];
+        rom[0x4000] = 0x4C; // JMP
+        rom[0x4001] = 0xF5;
+        rom[0x4002] = 0xC5;
+        cpu.load(rom);
+        cpu.reset();
+        cpu.program_counter = 0xC000;
+
+        assert_eq!(
+            cpu.trace(),
+            "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD"
+        );
+    }
+
+    #[test]
+    fn test_jmp_indirect_nmos_page_wrap_bug() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0x6c, 0xff, 0x01]); // JMP ($01FF)
+        cpu.reset();
+        cpu.mem_write(0x01ff, 0x00);
+        cpu.mem_write(0x0100, 0xab); // wrongly read as the high byte on NMOS
+        cpu.mem_write(0x0200, 0xcd); // correctly-carried high byte
+
+        let seen = std::cell::RefCell::new(Vec::new());
+        cpu.run_with_callback(|cpu| seen.borrow_mut().push(cpu.program_counter));
+
+        assert_eq!(seen.into_inner()[1], 0xab00);
+    }
+
+    #[test]
+    fn test_jmp_indirect_cmos_fixes_page_wrap() {
+        let mut cpu = CPU::new(Variant::Cmos65c02);
+        cpu.load(vec![0x6c, 0xff, 0x01]); // JMP ($01FF)
+        cpu.reset();
+        cpu.mem_write(0x01ff, 0x00);
+        cpu.mem_write(0x0100, 0xab);
+        cpu.mem_write(0x0200, 0xcd);
+
+        let seen = std::cell::RefCell::new(Vec::new());
+        cpu.run_with_callback(|cpu| seen.borrow_mut().push(cpu.program_counter));
+
+        assert_eq!(seen.into_inner()[1], 0xcd00);
+    }
 }
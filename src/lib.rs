@@ -0,0 +1,3 @@
+pub mod bus;
+pub mod cpu;
+pub mod opcodes;
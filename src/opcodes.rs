@@ -0,0 +1,76 @@
+use crate::cpu::AddressingMode;
+
+pub struct OpCode {
+    pub code: u8,
+    pub mnemonic: &'static str,
+    pub len: u8,
+    pub mode: AddressingMode,
+}
+
+impl OpCode {
+    const fn new(code: u8, mnemonic: &'static str, len: u8, mode: AddressingMode) -> Self {
+        OpCode {
+            code,
+            mnemonic,
+            len,
+            mode,
+        }
+    }
+}
+
+// Metadata for every opcode `CPU::run` currently decodes, keyed by its hex
+// code. `CPU::trace` uses this to disassemble instructions without
+// duplicating the decode table already living in `run`.
+pub fn lookup(code: u8) -> Option<OpCode> {
+    use AddressingMode::*;
+
+    Some(match code {
+        0xA9 => OpCode::new(0xA9, "LDA", 2, Immediate),
+        0xA5 => OpCode::new(0xA5, "LDA", 2, ZeroPage),
+        0xB5 => OpCode::new(0xB5, "LDA", 2, ZeroPageX),
+        0xAD => OpCode::new(0xAD, "LDA", 3, Absolute),
+        0xBD => OpCode::new(0xBD, "LDA", 3, AbsoluteX),
+        0xB9 => OpCode::new(0xB9, "LDA", 3, AbsoluteY),
+        0xA1 => OpCode::new(0xA1, "LDA", 2, IndirectX),
+        0xB1 => OpCode::new(0xB1, "LDA", 2, IndirectY),
+
+        0x69 => OpCode::new(0x69, "ADC", 2, Immediate),
+        0x65 => OpCode::new(0x65, "ADC", 2, ZeroPage),
+        0x75 => OpCode::new(0x75, "ADC", 2, ZeroPageX),
+        0x6D => OpCode::new(0x6D, "ADC", 3, Absolute),
+        0x7D => OpCode::new(0x7D, "ADC", 3, AbsoluteX),
+        0x79 => OpCode::new(0x79, "ADC", 3, AbsoluteY),
+        0x61 => OpCode::new(0x61, "ADC", 2, IndirectX),
+        0x71 => OpCode::new(0x71, "ADC", 2, IndirectY),
+
+        0xE9 => OpCode::new(0xE9, "SBC", 2, Immediate),
+        0xE5 => OpCode::new(0xE5, "SBC", 2, ZeroPage),
+        0xF5 => OpCode::new(0xF5, "SBC", 2, ZeroPageX),
+        0xED => OpCode::new(0xED, "SBC", 3, Absolute),
+        0xFD => OpCode::new(0xFD, "SBC", 3, AbsoluteX),
+        0xF9 => OpCode::new(0xF9, "SBC", 3, AbsoluteY),
+        0xE1 => OpCode::new(0xE1, "SBC", 2, IndirectX),
+        0xF1 => OpCode::new(0xF1, "SBC", 2, IndirectY),
+
+        0x38 => OpCode::new(0x38, "SEC", 1, NoneAddressing),
+        0x18 => OpCode::new(0x18, "CLC", 1, NoneAddressing),
+
+        0xAA => OpCode::new(0xAA, "TAX", 1, NoneAddressing),
+        0xE8 => OpCode::new(0xE8, "INX", 1, NoneAddressing),
+
+        0x48 => OpCode::new(0x48, "PHA", 1, NoneAddressing),
+        0x68 => OpCode::new(0x68, "PLA", 1, NoneAddressing),
+        0x08 => OpCode::new(0x08, "PHP", 1, NoneAddressing),
+        0x28 => OpCode::new(0x28, "PLP", 1, NoneAddressing),
+
+        0x4C => OpCode::new(0x4C, "JMP", 3, Absolute),
+        0x6C => OpCode::new(0x6C, "JMP", 3, Indirect),
+
+        0x20 => OpCode::new(0x20, "JSR", 3, Absolute),
+        0x60 => OpCode::new(0x60, "RTS", 1, NoneAddressing),
+        0x40 => OpCode::new(0x40, "RTI", 1, NoneAddressing),
+        0x00 => OpCode::new(0x00, "BRK", 1, NoneAddressing),
+
+        _ => return None,
+    })
+}